@@ -1,17 +1,98 @@
+use crate::Column::*;
 use clap::{App, Arg};
+use cutr::{parse_pos, PositionList};
+use feruca::Collator;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering::{self, *};
 use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    file1: String,
-    file2: String,
+    files: Vec<String>,
+    membership: Option<Membership>,
     show_col1: bool,
     show_col2: bool,
     show_col3: bool,
-    insensitive: bool,
+    order: SortOrder,
     delimiter: String,
+    check_order: OrderCheck,
+    key: Option<PositionList>,
+    separator: char,
+    total: bool,
+    stats: Option<StatsFormat>,
+    terminator: u8,
+    format: OutputFormat,
+}
+
+// The order the inputs are expected to be sorted in, which also decides
+// when two lines are considered the same.
+#[derive(Debug)]
+pub enum SortOrder {
+    Bytes,
+    CaseFold,
+    Unicode(RefCell<Collator>),
+    Numeric,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OrderCheck {
+    Warn,
+    Strict,
+    Off,
+}
+
+// Which lines to print when comparing more than two inputs (or when a
+// filter is asked for explicitly); `None` in `Config` is classic comm.
+#[derive(Debug, PartialEq)]
+pub enum Membership {
+    Any,
+    All,
+    Unique,
+    MissingFrom(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutputFormat {
+    Columns,
+    Diff,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    col1: usize,
+    col2: usize,
+    col3: usize,
+}
+
+enum Column<'a> {
+    Col1(&'a [u8]),
+    Col2(&'a [u8]),
+    Col3(&'a [u8]),
+}
+
+// One input of the comparison: the current line plus the previous one,
+// which is kept around to verify that the input is sorted. Records are
+// raw bytes so that file lists with non-UTF-8 names compare safely.
+struct Input {
+    name: String,
+    reader: Box<dyn BufRead>,
+    line: Vec<u8>,
+    prev: Vec<u8>,
+    line_num: usize,
+    has_line: bool,
+    unsorted: bool,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -19,6 +100,14 @@ pub fn get_args() -> MyResult<Config> {
         .version("0.1.0")
         .author("akash")
         .about("Rust comm")
+        .arg(
+            Arg::with_name("files")
+                .value_name("FILE")
+                .help("Sorted input files")
+                .required(true)
+                .multiple(true)
+                .min_values(2)
+            )
         .arg(
             Arg::with_name("col1")
                 .short("1")
@@ -43,6 +132,602 @@ pub fn get_args() -> MyResult<Config> {
                 .long("insensitive")
                 .help("Makes matches case insensitive")
                 .takes_value(false)
+                .conflicts_with("numeric")
+                .conflicts_with("order")
+            )
+        .arg(
+            Arg::with_name("numeric")
+                .short("n")
+                .long("numeric")
+                .help("Compare according to numerical value")
+                .takes_value(false)
+                .conflicts_with("order")
+            )
+        .arg(
+            Arg::with_name("order")
+                .long("order")
+                .value_name("ORDER")
+                .help("Order the inputs are sorted in")
+                .possible_values(&["bytes", "fold", "unicode", "numeric"])
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("delimiter")
+                .short("d")
+                .long("output-delimiter")
+                .value_name("DELIM")
+                .help("Output delimiter")
+                .default_value("\t")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("check_order")
+                .long("check-order")
+                .help("Fail if the input is not sorted")
+                .takes_value(false)
+                .conflicts_with("nocheck_order")
+            )
+        .arg(
+            Arg::with_name("nocheck_order")
+                .long("nocheck-order")
+                .help("Do not check that the input is sorted")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("key")
+                .short("k")
+                .long("key")
+                .value_name("FIELD")
+                .help("Compare on these fields instead of the whole line")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("separator")
+                .short("t")
+                .long("field-separator")
+                .value_name("SEP")
+                .help("Field separator for --key")
+                .default_value("\t")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("total")
+                .long("total")
+                .help("Output a summary line with the count of each column")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .value_name("FORMAT")
+                .help("Print only the count of each column")
+                .possible_values(&["text", "json"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with("total")
+            )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output layout")
+                .possible_values(&["columns", "diff", "json"])
+                .default_value("columns")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .help("Print only lines present in every file")
+                .takes_value(false)
+                .conflicts_with_all(&["unique", "missing_from"])
+            )
+        .arg(
+            Arg::with_name("unique")
+                .long("unique")
+                .help("Print only lines present in exactly one file")
+                .takes_value(false)
+                .conflicts_with("missing_from")
+            )
+        .arg(
+            Arg::with_name("missing_from")
+                .long("missing-from")
+                .value_name("K")
+                .help("Print only lines missing from file K")
+                .takes_value(true)
             )
         .get_matches();
+
+    let files = matches.values_of_lossy("files").unwrap();
+
+    let membership = if matches.is_present("all") {
+        Some(Membership::All)
+    } else if matches.is_present("unique") {
+        Some(Membership::Unique)
+    } else if let Some(val) = matches.value_of("missing_from") {
+        match val.parse::<usize>() {
+            Ok(n) if n > 0 && n <= files.len() => Some(Membership::MissingFrom(n - 1)),
+            _ => return Err(format!("illegal file number -- {}", val).into()),
+        }
+    } else if files.len() > 2 {
+        Some(Membership::Any)
+    } else {
+        None
+    };
+
+    if membership.is_some() {
+        if matches.is_present("col1") || matches.is_present("col2") || matches.is_present("col3") {
+            return Err(From::from("-1, -2 and -3 only apply to two input files"));
+        }
+        if matches.is_present("total") || matches.is_present("stats") {
+            return Err(From::from("--total and --stats only apply to two input files"));
+        }
+        if matches.value_of("format") == Some("diff") {
+            return Err(From::from("--format=diff only applies to two input files"));
+        }
+    }
+
+    let format = match matches.value_of("format") {
+        Some("diff") => OutputFormat::Diff,
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Columns,
+    };
+
+    let order = if matches.is_present("insensitive") {
+        SortOrder::CaseFold
+    } else if matches.is_present("numeric") {
+        SortOrder::Numeric
+    } else {
+        match matches.value_of("order") {
+            Some("fold") => SortOrder::CaseFold,
+            Some("unicode") => SortOrder::Unicode(RefCell::new(Collator::default())),
+            Some("numeric") => SortOrder::Numeric,
+            _ => SortOrder::Bytes,
+        }
+    };
+
+    let stats = if matches.is_present("stats") {
+        match matches.value_of("stats") {
+            Some("json") => Some(StatsFormat::Json),
+            _ => Some(StatsFormat::Text),
+        }
+    } else {
+        None
+    };
+
+    let key = matches
+        .value_of("key")
+        .map(parse_pos)
+        .transpose()
+        .map_err(|e| format!("illegal key field -- {}", e))?;
+
+    let sep = matches.value_of("separator").unwrap();
+    let mut sep_chars = sep.chars();
+    let separator = match (sep_chars.next(), sep_chars.next()) {
+        (Some(c), None) => c,
+        _ => {
+            return Err(
+                format!("--field-separator \"{}\" must be a single character", sep).into(),
+            )
+        }
+    };
+
+    let check_order = if matches.is_present("check_order") {
+        OrderCheck::Strict
+    } else if matches.is_present("nocheck_order") {
+        OrderCheck::Off
+    } else {
+        OrderCheck::Warn
+    };
+
+    Ok(Config {
+        files,
+        membership,
+        show_col1: !matches.is_present("col1"),
+        show_col2: !matches.is_present("col2"),
+        show_col3: !matches.is_present("col3"),
+        order,
+        delimiter: matches.value_of("delimiter").unwrap().to_string(),
+        check_order,
+        key,
+        separator,
+        total: matches.is_present("total"),
+        stats,
+        terminator: if matches.is_present("zero_terminated") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        format,
+    })
+}
+
+fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
+    match filename {
+        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
+        _ => Ok(Box::new(BufReader::new(
+            File::open(filename).map_err(|e| format!("{}: {}", filename, e))?,
+        ))),
+    }
+}
+
+impl Input {
+    fn new(name: &str) -> MyResult<Input> {
+        Ok(Input {
+            name: name.to_string(),
+            reader: open(name)?,
+            line: Vec::new(),
+            prev: Vec::new(),
+            line_num: 0,
+            has_line: false,
+            unsorted: false,
+        })
+    }
+
+    // Reads the next record without its terminator and checks it against
+    // the previous one according to `--check-order`/`--nocheck-order`.
+    fn advance(&mut self, config: &Config) -> MyResult<()> {
+        std::mem::swap(&mut self.line, &mut self.prev);
+        let buf = &mut self.line;
+        buf.clear();
+        self.has_line = self.reader.read_until(config.terminator, buf)? > 0;
+        if buf.last() == Some(&config.terminator) {
+            buf.pop();
+        }
+        if !self.has_line {
+            return Ok(());
+        }
+        self.line_num += 1;
+
+        if self.line_num > 1
+            && config.check_order != OrderCheck::Off
+            && compare(config, &self.prev, &self.line) == Greater
+        {
+            let msg = format!("{}:{}: is not in sorted order", self.name, self.line_num);
+            if config.check_order == OrderCheck::Strict {
+                return Err(msg.into());
+            }
+            if !self.unsorted {
+                eprintln!("{}", msg);
+                self.unsorted = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Splits a record on the bytes of the field separator.
+fn split_fields<'a>(line: &'a [u8], sep: &[u8]) -> Vec<&'a [u8]> {
+    let mut fields = vec![];
+    let mut rest = line;
+    while let Some(i) = rest.windows(sep.len()).position(|w| w == sep) {
+        fields.push(&rest[..i]);
+        rest = &rest[i + sep.len()..];
+    }
+    fields.push(rest);
+    fields
+}
+
+// Picks out the fields selected by `-k` and rejoins them with the
+// separator; without `-k` the whole line is the key.
+fn extract_key<'a>(config: &Config, line: &'a [u8]) -> Cow<'a, [u8]> {
+    match &config.key {
+        None => Cow::Borrowed(line),
+        Some(pos) => {
+            let mut sep = [0; 4];
+            let sep = config.separator.encode_utf8(&mut sep).as_bytes();
+            let fields = split_fields(line, sep);
+            let selected: Vec<&[u8]> = pos
+                .iter()
+                .flat_map(|range| {
+                    let start = range.start.min(fields.len());
+                    let end = range.end.min(fields.len());
+                    fields[start..end].iter().copied()
+                })
+                .collect();
+            Cow::Owned(selected.join(sep))
+        }
+    }
+}
+
+// Parses the leading number of a line the way `sort -n` does; lines
+// without one compare as zero.
+fn numeric_prefix(s: &str) -> f64 {
+    let s = s.trim_start();
+    let mut end = 0;
+    let mut seen_dot = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '-' if i == 0 => {}
+            '.' if !seen_dot => seen_dot = true,
+            c if c.is_ascii_digit() => {}
+            _ => break,
+        }
+        end = i + c.len_utf8();
+    }
+    s[..end].parse().unwrap_or(0.0)
+}
+
+// Lowercases the valid UTF-8 in a record and keeps any invalid bytes as
+// they are, so records that differ only in those bytes stay distinct.
+fn fold_case(s: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(s.len());
+    for chunk in s.utf8_chunks() {
+        ret.extend_from_slice(chunk.valid().to_lowercase().as_bytes());
+        ret.extend_from_slice(chunk.invalid());
+    }
+    ret
+}
+
+// The records are raw bytes; the numeric order reads the number from the
+// decoded text and falls back to the bytes on a tie.
+impl SortOrder {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            SortOrder::Bytes => a.cmp(b),
+            SortOrder::CaseFold => fold_case(a).cmp(&fold_case(b)),
+            SortOrder::Unicode(collator) => collator.borrow_mut().collate(a, b),
+            // Like `sort -n`, equal numbers fall back to the bytes so
+            // that only identical keys end up in the third column.
+            SortOrder::Numeric => numeric_prefix(&String::from_utf8_lossy(a))
+                .partial_cmp(&numeric_prefix(&String::from_utf8_lossy(b)))
+                .unwrap_or(Equal)
+                .then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+fn compare(config: &Config, line1: &[u8], line2: &[u8]) -> Ordering {
+    let key1 = extract_key(config, line1);
+    let key2 = extract_key(config, line2);
+    config.order.compare(&key1, &key2)
+}
+
+// Bytes that are not valid UTF-8 are written as `\u00XX` escapes so that
+// records differing only in them still print differently.
+fn json_escape(s: &[u8]) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for chunk in s.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => ret.push_str("\\\""),
+                '\\' => ret.push_str("\\\\"),
+                '\n' => ret.push_str("\\n"),
+                '\r' => ret.push_str("\\r"),
+                '\t' => ret.push_str("\\t"),
+                c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+                c => ret.push(c),
+            }
+        }
+        for b in chunk.invalid() {
+            ret.push_str(&format!("\\u{:04x}", b));
+        }
+    }
+    ret
+}
+
+fn print(out: &mut impl Write, config: &Config, col: Column) -> MyResult<()> {
+    let (num, val, show) = match col {
+        Col1(val) => (1, val, config.show_col1),
+        Col2(val) => (2, val, config.show_col2),
+        Col3(val) => (3, val, config.show_col3),
+    };
+    if !show {
+        return Ok(());
+    }
+
+    match config.format {
+        OutputFormat::Columns => {
+            if num > 1 && config.show_col1 {
+                write!(out, "{}", config.delimiter)?;
+            }
+            if num > 2 && config.show_col2 {
+                write!(out, "{}", config.delimiter)?;
+            }
+            out.write_all(val)?;
+        }
+        OutputFormat::Diff => {
+            let prefix = match num {
+                1 => "<",
+                2 => ">",
+                _ => "=",
+            };
+            write!(out, "{} ", prefix)?;
+            out.write_all(val)?;
+        }
+        OutputFormat::Json => {
+            write!(
+                out,
+                "{{\"column\":{},\"line\":\"{}\"}}",
+                num,
+                json_escape(val)
+            )?;
+        }
+    }
+    out.write_all(&[config.terminator])?;
+    Ok(())
+}
+
+pub fn run(config: Config) -> MyResult<()> {
+    if config.files.iter().filter(|f| *f == "-").count() > 1 {
+        return Err(From::from(if config.files.len() == 2 {
+            "Both input files cannot be STDIN (\"-\")"
+        } else {
+            "Only one input file can be STDIN (\"-\")"
+        }));
+    }
+
+    match &config.membership {
+        Some(membership) => compare_many(&config, membership),
+        None => compare_two(&config),
+    }
+}
+
+fn compare_two(config: &Config) -> MyResult<()> {
+    let file1 = &config.files[0];
+    let file2 = &config.files[1];
+
+    let mut input1 = Input::new(file1)?;
+    let mut input2 = Input::new(file2)?;
+    let mut out = BufWriter::new(io::stdout());
+    let mut counts = Counts::default();
+    let show_lines = config.stats.is_none();
+
+    // Only the current line of each input is held in memory; whichever
+    // side sorts first is printed and advanced.
+    input1.advance(config)?;
+    input2.advance(config)?;
+
+    while input1.has_line || input2.has_line {
+        let order = match (input1.has_line, input2.has_line) {
+            (true, true) => compare(config, &input1.line, &input2.line),
+            (true, false) => Less,
+            _ => Greater,
+        };
+        match order {
+            Equal => {
+                counts.col3 += 1;
+                if show_lines {
+                    print(&mut out, config, Col3(&input1.line))?;
+                }
+                input1.advance(config)?;
+                input2.advance(config)?;
+            }
+            Less => {
+                counts.col1 += 1;
+                if show_lines {
+                    print(&mut out, config, Col1(&input1.line))?;
+                }
+                input1.advance(config)?;
+            }
+            Greater => {
+                counts.col2 += 1;
+                if show_lines {
+                    print(&mut out, config, Col2(&input2.line))?;
+                }
+                input2.advance(config)?;
+            }
+        }
+    }
+
+    match config.stats {
+        Some(StatsFormat::Text) => {
+            writeln!(out, "only in {}: {}", file1, counts.col1)?;
+            writeln!(out, "only in {}: {}", file2, counts.col2)?;
+            writeln!(out, "in both: {}", counts.col3)?;
+        }
+        Some(StatsFormat::Json) => {
+            writeln!(
+                out,
+                "{{\"only_file1\":{},\"only_file2\":{},\"common\":{}}}",
+                counts.col1, counts.col2, counts.col3
+            )?;
+        }
+        None => {
+            if config.total {
+                write!(
+                    out,
+                    "{1}{0}{2}{0}{3}{0}total",
+                    config.delimiter, counts.col1, counts.col2, counts.col3
+                )?;
+                out.write_all(&[config.terminator])?;
+            }
+        }
+    }
+
+    out.flush()?;
+    if input1.unsorted || input2.unsorted {
+        return Err(From::from("input is not in sorted order"));
+    }
+    Ok(())
+}
+
+// Lockstep merge over any number of inputs: the smallest current line is
+// printed along with a mask of the files that contain it, and every
+// input holding that line is advanced.
+fn compare_many(config: &Config, membership: &Membership) -> MyResult<()> {
+    let mut inputs = config
+        .files
+        .iter()
+        .map(|f| Input::new(f))
+        .collect::<MyResult<Vec<_>>>()?;
+    let mut out = BufWriter::new(io::stdout());
+
+    for input in inputs.iter_mut() {
+        input.advance(config)?;
+    }
+
+    loop {
+        let mut min: Option<usize> = None;
+        for (i, input) in inputs.iter().enumerate() {
+            if input.has_line
+                && min.is_none_or(|m| compare(config, &input.line, &inputs[m].line) == Less)
+            {
+                min = Some(i);
+            }
+        }
+        let min = match min {
+            Some(m) => m,
+            None => break,
+        };
+
+        let present: Vec<bool> = inputs
+            .iter()
+            .map(|input| {
+                input.has_line && compare(config, &input.line, &inputs[min].line) == Equal
+            })
+            .collect();
+        let num_present = present.iter().filter(|&&p| p).count();
+        let show = match membership {
+            Membership::Any => true,
+            Membership::All => num_present == inputs.len(),
+            Membership::Unique => num_present == 1,
+            Membership::MissingFrom(k) => !present[*k],
+        };
+
+        if show {
+            let line = &inputs[min].line;
+            match config.format {
+                OutputFormat::Json => {
+                    let files: Vec<String> = present
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &p)| p)
+                        .map(|(i, _)| (i + 1).to_string())
+                        .collect();
+                    write!(
+                        out,
+                        "{{\"files\":[{}],\"line\":\"{}\"}}",
+                        files.join(","),
+                        json_escape(line)
+                    )?;
+                }
+                _ => {
+                    let mask: String = present.iter().map(|&p| if p { '1' } else { '0' }).collect();
+                    write!(out, "{}{}", mask, config.delimiter)?;
+                    out.write_all(line)?;
+                }
+            }
+            out.write_all(&[config.terminator])?;
+        }
+
+        for (input, &p) in inputs.iter_mut().zip(present.iter()) {
+            if p {
+                input.advance(config)?;
+            }
+        }
+    }
+
+    out.flush()?;
+    if inputs.iter().any(|input| input.unsorted) {
+        return Err(From::from("input is not in sorted order"));
+    }
+    Ok(())
 }
@@ -8,6 +8,24 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FILE1: &str = "tests/inputs/file1.txt";
 const FILE2: &str = "tests/inputs/file2.txt";
 const BLANK: &str = "tests/inputs/blank.txt";
+const CRLF: &str = "tests/inputs/crlf.txt";
+const UNSORTED: &str = "tests/inputs/unsorted.txt";
+const USERS1: &str = "tests/inputs/users1.csv";
+const USERS2: &str = "tests/inputs/users2.csv";
+const ZERO1: &str = "tests/inputs/zero1.txt";
+const ZERO2: &str = "tests/inputs/zero2.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const LATIN2: &str = "tests/inputs/latin2.txt";
+const NUMBERS1: &str = "tests/inputs/numbers1.txt";
+const NUMBERS2: &str = "tests/inputs/numbers2.txt";
+const MIXED1: &str = "tests/inputs/mixed1.txt";
+const MIXED2: &str = "tests/inputs/mixed2.txt";
+const WORDS1: &str = "tests/inputs/words1.txt";
+const WORDS2: &str = "tests/inputs/words2.txt";
+const TABS: &str = "tests/inputs/tabs.txt";
+const HOST1: &str = "tests/inputs/host1.txt";
+const HOST2: &str = "tests/inputs/host2.txt";
+const HOST3: &str = "tests/inputs/host3.txt";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -340,6 +358,416 @@ fn blank_file1() -> TestResult {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn crlf_stdin() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/crlf_stdin.out")?;
+    Command::cargo_bin(PRG)?
+        .args([CRLF, "-"])
+        .write_stdin("a\nc\n")
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+// order checking
+// --------------------------------------------------
+#[test]
+fn unsorted_warns() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/unsorted_file2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([UNSORTED, FILE2])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::contains(
+            "tests/inputs/unsorted.txt:3: is not in sorted order",
+        ))
+        .stderr(predicate::str::contains("input is not in sorted order"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_nocheck_order() -> TestResult {
+    run(
+        &["--nocheck-order", UNSORTED, FILE2],
+        "tests/expected/unsorted_file2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_check_order() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/unsorted_file2.check.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", UNSORTED, FILE2])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::contains(
+            "tests/inputs/unsorted.txt:3: is not in sorted order",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sorted_check_order() -> TestResult {
+    run(
+        &["--check-order", FILE1, FILE2],
+        "tests/expected/file1_file2.out",
+    )
+}
+
+// --------------------------------------------------
+// key fields
+// --------------------------------------------------
+#[test]
+fn users1_users2() -> TestResult {
+    run(&[USERS1, USERS2], "tests/expected/users1_users2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn users1_users2_key1() -> TestResult {
+    run(
+        &["-k", "1", "-t", ",", USERS1, USERS2],
+        "tests/expected/users1_users2.key1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn users1_users2_key1_2() -> TestResult {
+    run(
+        &["-k", "1-2", "-t", ",", USERS1, USERS2],
+        "tests/expected/users1_users2.key1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-k", "0", USERS1, USERS2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal key field -- illegal list value: \"0\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_separator() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-k", "1", "-t", ",,", USERS1, USERS2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--field-separator \",,\" must be a single character",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+// summaries
+// --------------------------------------------------
+#[test]
+fn file1_file2_total() -> TestResult {
+    run(
+        &["--total", FILE1, FILE2],
+        "tests/expected/file1_file2.total.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_12_total_delim() -> TestResult {
+    run(
+        &["-12", "--total", "-d", ":", FILE1, FILE2],
+        "tests/expected/file1_file2.12.total.delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_stats() -> TestResult {
+    run(
+        &["--stats", FILE1, FILE2],
+        "tests/expected/file1_file2.stats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_stats_json() -> TestResult {
+    run(
+        &["--stats=json", FILE1, FILE2],
+        "tests/expected/file1_file2.stats.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_stats_json_i() -> TestResult {
+    run(
+        &["-i", "--stats=json", FILE1, FILE2],
+        "tests/expected/file1_file2.stats.i.json.out",
+    )
+}
+
+// --------------------------------------------------
+// NUL-terminated records
+// --------------------------------------------------
+#[test]
+fn zero1_zero2_z() -> TestResult {
+    run(&["-z", ZERO1, ZERO2], "tests/expected/zero1_zero2.z.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn zero1_zero2_z_total() -> TestResult {
+    run(
+        &["--zero-terminated", "--total", ZERO1, ZERO2],
+        "tests/expected/zero1_zero2.z.total.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_latin2_z() -> TestResult {
+    let expected = fs::read("tests/expected/latin1_latin2.z.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-z", LATIN1, LATIN2])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_latin2_i_z() -> TestResult {
+    let expected = fs::read("tests/expected/latin1_latin2.i.z.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-i", "-z", LATIN1, LATIN2])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_latin2_z_json() -> TestResult {
+    run(
+        &["-z", "--format", "json", LATIN1, LATIN2],
+        "tests/expected/latin1_latin2.z.json.out",
+    )
+}
+
+// --------------------------------------------------
+// sort orders
+// --------------------------------------------------
+#[test]
+fn numbers1_numbers2_n() -> TestResult {
+    run(
+        &["-n", NUMBERS1, NUMBERS2],
+        "tests/expected/numbers1_numbers2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn numbers1_numbers2_order_numeric() -> TestResult {
+    run(
+        &["--order", "numeric", NUMBERS1, NUMBERS2],
+        "tests/expected/numbers1_numbers2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed1_mixed2_n() -> TestResult {
+    run(
+        &["-n", "--check-order", MIXED1, MIXED2],
+        "tests/expected/mixed1_mixed2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn numbers1_numbers2_bytes_unsorted() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", NUMBERS1, NUMBERS2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "tests/inputs/numbers1.txt:2: is not in sorted order",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn words1_words2_unicode() -> TestResult {
+    run(
+        &["--check-order", "--order", "unicode", WORDS1, WORDS2],
+        "tests/expected/words1_words2.unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_123_order_fold() -> TestResult {
+    run(
+        &["-123", "--order", "fold", FILE1, FILE2],
+        "tests/expected/file1_file2.123.i.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_insensitive_numeric() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-i", "-n", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+// output formats
+// --------------------------------------------------
+#[test]
+fn file1_file2_diff() -> TestResult {
+    run(
+        &["--format", "diff", FILE1, FILE2],
+        "tests/expected/file1_file2.diff.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_3_diff() -> TestResult {
+    run(
+        &["-3", "--format=diff", FILE1, FILE2],
+        "tests/expected/file1_file2.3.diff.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_json() -> TestResult {
+    run(
+        &["--format", "json", FILE1, FILE2],
+        "tests/expected/file1_file2.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tabs_file2_json() -> TestResult {
+    run(
+        &["--format", "json", TABS, FILE2],
+        "tests/expected/tabs_file2.json.out",
+    )
+}
+
+// --------------------------------------------------
+// n-way comparison
+// --------------------------------------------------
+#[test]
+fn host1_host2_host3() -> TestResult {
+    run(
+        &[HOST1, HOST2, HOST3],
+        "tests/expected/host1_host2_host3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn host1_host2_host3_all() -> TestResult {
+    run(
+        &["--all", HOST1, HOST2, HOST3],
+        "tests/expected/host1_host2_host3.all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn host1_host2_host3_unique() -> TestResult {
+    run(
+        &["--unique", HOST1, HOST2, HOST3],
+        "tests/expected/host1_host2_host3.unique.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn host1_host2_host3_missing3() -> TestResult {
+    run(
+        &["--missing-from", "3", HOST1, HOST2, HOST3],
+        "tests/expected/host1_host2_host3.missing3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn host1_host2_host3_json() -> TestResult {
+    run(
+        &["--format", "json", HOST1, HOST2, HOST3],
+        "tests/expected/host1_host2_host3.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file1_all() -> TestResult {
+    run(&["--all", FILE1, FILE1], "tests/expected/file1_file1.all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_missing_from() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--missing-from", "4", HOST1, HOST2, HOST3])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal file number -- 4"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_columns_with_many_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-3", HOST1, HOST2, HOST3])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "-1, -2 and -3 only apply to two input files",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_many_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([HOST1, "-", "-"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Only one input file can be STDIN (\"-\")",
+        ));
+    Ok(())
+}
+
 //// --------------------------------------------------
 //#[test]
 //fn file1_blanks() -> TestResult {
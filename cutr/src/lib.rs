@@ -8,7 +8,7 @@ use std::io::{BufRead, BufReader};
 use std::ops::Range;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
-type PositionList = Vec<Range<usize>>;
+pub type PositionList = Vec<Range<usize>>;
 
 #[derive(Debug)]
 pub enum Extract {
@@ -246,7 +246,7 @@ fn is_numeric(s: &str) -> bool {
     s.chars().all(|c| c.is_digit(10))
 }
 
-fn parse_pos(range: &str) -> MyResult<PositionList> {
+pub fn parse_pos(range: &str) -> MyResult<PositionList> {
     let mut pos_list = PositionList::new();
     for str_range in range.split(",") {
         let start_str: String = str_range.chars().take_while(|&c| c != '-').collect();
@@ -1,15 +1,26 @@
 use clap::{App, Arg};
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read, Stdin, Write};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
-    bytes: Option<usize>,
+    lines: Count,
+    bytes: Option<Count>,
+    headers: bool,
+    terminator: u8,
+}
+
+// How much of each file to print: the first N lines or bytes, or all but
+// the last N.
+#[derive(Debug, PartialEq)]
+pub enum Count {
+    First(usize),
+    AllBut(usize),
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -32,6 +43,7 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Number of lines")
                 .default_value("10")
                 .takes_value(true)
+                .allow_hyphen_values(true)
         )
         .arg(
             Arg::with_name("bytes")
@@ -40,30 +52,141 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("BYTES")
                 .help("Number of bytes")
                 .takes_value(true)
+                .allow_hyphen_values(true)
                 .conflicts_with("lines")
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .alias("silent")
+                .help("Never print headers giving file names")
+                .takes_value(false)
+                .overrides_with("verbose")
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Always print headers giving file names")
+                .takes_value(false)
+                .overrides_with("quiet")
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .takes_value(false)
         ).get_matches();
+    let files = matches.values_of_lossy("files").unwrap();
     Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
+        headers: if matches.is_present("quiet") {
+            false
+        } else {
+            matches.is_present("verbose") || files.len() > 1
+        },
+        terminator: if matches.is_present("zero_terminated") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        files,
         lines: matches
             .value_of("lines")
-            .map(parse_positive_int)
+            .map(parse_count)
             .transpose()
             .map_err(|e| format!("illegal line count -- {}", e))?.unwrap(),
         bytes: matches
             .value_of("bytes")
-            .map(parse_positive_int)
+            .map(parse_count)
             .transpose()
             .map_err(|e| format!("illegal byte count -- {}", e))?,
     })
 }
 
 fn parse_positive_int(val: &str) -> MyResult<usize> {
-    match val.parse() {
-        Ok(n) if n > 0 => Ok(n),
-        _ => Err(From::from(val)),
+    match parse_int(val)? {
+        0 => Err(From::from(val)),
+        n => Ok(n),
     }
 }
 
+// Accepts the multiplier suffixes of GNU head: b (512), K or KiB (1024),
+// KB (1000), and likewise M, G, T, P, E, Z, Y, R and Q.
+fn parse_int(val: &str) -> MyResult<usize> {
+    let digits = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (num, suffix) = val.split_at(digits);
+    let too_large = || format!("{}: value too large", val);
+
+    let multiplier = match suffix {
+        "" => Some(1),
+        "b" => Some(512),
+        _ => {
+            let mut chars = suffix.chars();
+            let power = match chars.next() {
+                Some('k' | 'K') => 1,
+                Some('M') => 2,
+                Some('G') => 3,
+                Some('T') => 4,
+                Some('P') => 5,
+                Some('E') => 6,
+                Some('Z') => 7,
+                Some('Y') => 8,
+                Some('R') => 9,
+                Some('Q') => 10,
+                _ => return Err(From::from(val)),
+            };
+            let base: usize = match chars.as_str() {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return Err(From::from(val)),
+            };
+            base.checked_pow(power)
+        }
+    };
+
+    match num.parse::<usize>() {
+        Ok(0) => Ok(0),
+        Ok(n) => Ok(multiplier
+            .and_then(|m| n.checked_mul(m))
+            .ok_or_else(too_large)?),
+        Err(_) if !num.is_empty() => Err(From::from(too_large())),
+        Err(_) => Err(From::from(val)),
+    }
+}
+
+// A leading '-' means all but the last N, where N may be 0 to keep
+// everything, as with GNU head.
+fn parse_count(val: &str) -> MyResult<Count> {
+    match val.strip_prefix('-') {
+        Some(num) => parse_int(num)
+            .map(Count::AllBut)
+            .map_err(|e| From::from(format!("-{}", e))),
+        None => parse_positive_int(val).map(Count::First),
+    }
+}
+
+#[test]
+fn test_parse_count() {
+    assert_eq!(parse_count("3").unwrap(), Count::First(3));
+    assert_eq!(parse_count("-3").unwrap(), Count::AllBut(3));
+    assert_eq!(parse_count("-0").unwrap(), Count::AllBut(0));
+    assert!(parse_count("0").is_err());
+
+    let res = parse_count("-foo");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "-foo".to_string());
+
+    assert!(parse_count("--3").is_err());
+
+    let res = parse_count("-99999999999999999999");
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "-99999999999999999999: value too large".to_string()
+    );
+}
+
 #[test]
 fn test_parse_positive_int() {
     let res = parse_positive_int("3");
@@ -79,35 +202,70 @@ fn test_parse_positive_int() {
     assert_eq!(res.unwrap_err().to_string(), "0".to_string());
 }
 
-pub fn run(config: Config) -> MyResult<()> { 
-    let length = config.files.len();
-    for filename in config.files {
-        match open(&filename) {
+#[test]
+fn test_parse_positive_int_suffix() {
+    assert_eq!(parse_positive_int("2b").unwrap(), 1024);
+    assert_eq!(parse_positive_int("3K").unwrap(), 3072);
+    assert_eq!(parse_positive_int("3k").unwrap(), 3072);
+    assert_eq!(parse_positive_int("3KiB").unwrap(), 3072);
+    assert_eq!(parse_positive_int("3KB").unwrap(), 3000);
+    assert_eq!(parse_positive_int("10M").unwrap(), 10 * 1024 * 1024);
+    assert_eq!(parse_positive_int("10MB").unwrap(), 10_000_000);
+    assert_eq!(parse_positive_int("1MiB").unwrap(), 1 << 20);
+    assert_eq!(parse_positive_int("1G").unwrap(), 1 << 30);
+    assert_eq!(parse_positive_int("1GB").unwrap(), 1_000_000_000);
+
+    let res = parse_positive_int("10X");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "10X".to_string());
+
+    let res = parse_positive_int("10KX");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "10KX".to_string());
+
+    let res = parse_positive_int("K");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "K".to_string());
+
+    let res = parse_positive_int("0K");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "0K".to_string());
+
+    let res = parse_positive_int("1Q");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "1Q: value too large".to_string());
+
+    let res = parse_positive_int("99999999999999999999");
+    assert!(res.is_err());
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "99999999999999999999: value too large".to_string()
+    );
+
+    let res = parse_positive_int("20000000000000E");
+    assert!(res.is_err());
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "20000000000000E: value too large".to_string()
+    );
+}
+
+pub fn run(config: Config) -> MyResult<()> {
+    let mut first = true;
+    for filename in &config.files {
+        match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(mut reader) => {
-                if length > 1 {
-                    println!("==> {} <==", filename);
+            Ok(input) => {
+                if config.headers {
+                    println!("{}==> {} <==", if first { "" } else { "\n" }, filename);
                 }
-                match config.bytes {
-                    // Print the corresponding number of bytes
-                    Some(num_bytes) => {
-                        let mut buf = vec![0u8; num_bytes];
-                        let bytes = reader.read_exact(&mut buf);
-                        if bytes == 0 {
-                            break;
-                        }
-                        let s = String::from_utf8_lossy(&buf);
-                        print!("{}", s);
-                    },
-                    // Print corresponding number of lines
-                    None => {
-                        for _line_num in 0..config.lines {
-                            let mut string = String::new();
-                            if let Ok(_) = reader.read_line(&mut string) {
-                                print!("{}", string);
-                            }
-                        }
-                    },
+                first = false;
+                let result = match input {
+                    Input::Stdin(reader) => head(&config, reader),
+                    Input::File(reader) => head(&config, reader),
+                };
+                if let Err(err) = result {
+                    eprintln!("{}: {}", filename, err);
                 }
             },
         }
@@ -115,9 +273,96 @@ pub fn run(config: Config) -> MyResult<()> {
     Ok(())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
+// Generic over the reader so that `io::copy` can see a concrete file and
+// use copy_file_range, sendfile or splice where the platform supports them.
+fn head(config: &Config, mut reader: impl BufRead) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    match (&config.bytes, &config.lines) {
+        // Print everything but the last bytes
+        (Some(Count::AllBut(num_bytes)), _) => {
+            drop_last_bytes(reader, *num_bytes)?;
+        },
+        // Print the corresponding number of bytes
+        (Some(Count::First(num_bytes)), _) => {
+            stdout.flush()?;
+            io::copy(&mut reader.take(*num_bytes as u64), &mut stdout)?;
+        },
+        // Print everything but the last lines
+        (None, Count::AllBut(num_lines)) => {
+            drop_last_lines(reader, *num_lines, config.terminator)?;
+        },
+        // Print corresponding number of lines
+        (None, Count::First(num_lines)) => {
+            let mut line = Vec::new();
+            for _line_num in 0..*num_lines {
+                line.clear();
+                if reader.read_until(config.terminator, &mut line)? == 0 {
+                    break;
+                }
+                stdout.write_all(&line)?;
+            }
+        },
+    }
+    Ok(())
+}
+
+// Size of the read buffer, which bounds memory use whatever the count.
+const BUF_SIZE: usize = 128 * 1024;
+
+enum Input {
+    Stdin(BufReader<Stdin>),
+    File(BufReader<File>),
+}
+
+fn open(filename: &str) -> MyResult<Input> {
     match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => Ok(Input::Stdin(BufReader::with_capacity(BUF_SIZE, io::stdin()))),
+        _ => Ok(Input::File(BufReader::with_capacity(BUF_SIZE, File::open(filename)?))),
+    }
+}
+
+// Holds back the last `num_lines` lines in a ring buffer and prints each
+// older line as it is pushed out.
+fn drop_last_lines(
+    mut reader: impl BufRead,
+    num_lines: usize,
+    terminator: u8,
+) -> MyResult<()> {
+    let mut stdout = io::stdout();
+    let mut ring = VecDeque::with_capacity(num_lines.min(1024) + 1);
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(terminator, &mut line)? == 0 {
+            break;
+        }
+        ring.push_back(line);
+        if ring.len() > num_lines {
+            stdout.write_all(&ring.pop_front().unwrap())?;
+        }
     }
+    Ok(())
+}
+
+// Same as `drop_last_lines` for bytes, copying whatever no longer fits in
+// the last `num_bytes`.
+fn drop_last_bytes(mut reader: impl Read, num_bytes: usize) -> MyResult<()> {
+    let mut stdout = io::stdout();
+    let mut ring = VecDeque::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        ring.extend(&buf[..n]);
+        if ring.len() > num_bytes {
+            let excess = ring.len() - num_bytes;
+            let (front, back) = ring.as_slices();
+            let from_front = excess.min(front.len());
+            stdout.write_all(&front[..from_front])?;
+            stdout.write_all(&back[..excess - from_front])?;
+            ring.drain(..excess);
+        }
+    }
+    Ok(())
 }
@@ -1,11 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::io::prelude::*;
-use std::{
-    error::Error,
-    fs::{self, File},
-};
+use std::{error::Error, fs};
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -15,6 +11,8 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TEN: &str = "./tests/inputs/ten.txt";
+const LATIN1: &str = "./tests/inputs/latin1.txt";
+const NUL: &str = "./tests/inputs/nul.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -63,6 +61,46 @@ fn dies_bad_lines() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bytes_too_large() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1Q", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "illegal byte count -- 1Q: value too large",
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_negative_lines_too_large() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-1Q", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "illegal line count -- -1Q: value too large",
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_suffix() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "10KX", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal line count -- 10KX"));
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bytes_and_lines() -> TestResult {
@@ -93,17 +131,13 @@ fn skips_bad_file() -> TestResult {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    let expected = fs::read(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(expected);
 
     Ok(())
 }
@@ -114,18 +148,14 @@ fn run_stdin(
     input_file: &str,
     expected_file: &str,
 ) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-    let input = fs::read_to_string(input_file)?;
+    let expected = fs::read(expected_file)?;
+    let input = fs::read(input_file)?;
 
     Command::cargo_bin(PRG)?
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(expected);
 
     Ok(())
 }
@@ -374,6 +404,95 @@ fn ten_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], TEN, "tests/expected/ten.txt.c4.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn latin1_n2() -> TestResult {
+    run(&[LATIN1, "-n", "2"], "tests/expected/latin1.txt.n2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn one_verbose() -> TestResult {
+    run(&["-v", "-n", "1", ONE], "tests/expected/one.txt.v.n1.out")
+}
+
+#[test]
+fn one_two_quiet() -> TestResult {
+    run(&["-q", "-n", "1", ONE, TWO], "tests/expected/one_two.q.n1.out")
+}
+
+#[test]
+fn one_two_silent() -> TestResult {
+    run(
+        &["--silent", "-n", "1", ONE, TWO],
+        "tests/expected/one_two.q.n1.out",
+    )
+}
+
+#[test]
+fn one_verbose_then_quiet() -> TestResult {
+    run(&["-v", "-q", "-n", "1", ONE, TWO], "tests/expected/one_two.q.n1.out")
+}
+
+#[test]
+fn nul_zero_terminated_n2() -> TestResult {
+    run(&["-z", "-n", "2", NUL], "tests/expected/nul.txt.z.n2.out")
+}
+
+#[test]
+fn nul_zero_terminated_n_minus1() -> TestResult {
+    run(
+        &["--zero-terminated", "-n", "-1", NUL],
+        "tests/expected/nul.txt.z.n-1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn three_n_minus1() -> TestResult {
+    run(&[THREE, "-n", "-1"], "tests/expected/three.txt.n-1.out")
+}
+
+#[test]
+fn three_c_minus20() -> TestResult {
+    run(&[THREE, "-c", "-20"], "tests/expected/three.txt.c-20.out")
+}
+
+#[test]
+fn ten_n_minus3() -> TestResult {
+    run(&[TEN, "-n", "-3"], "tests/expected/ten.txt.n-3.out")
+}
+
+#[test]
+fn ten_n_minus20() -> TestResult {
+    run(&[TEN, "--lines=-20"], "tests/expected/ten.txt.n-20.out")
+}
+
+#[test]
+fn ten_c_minus5() -> TestResult {
+    run(&[TEN, "-c", "-5"], "tests/expected/ten.txt.c-5.out")
+}
+
+#[test]
+fn one_n_minus0() -> TestResult {
+    run(&[ONE, "-n", "-0"], "tests/expected/one.txt.out")
+}
+
+#[test]
+fn ten_c_minus0() -> TestResult {
+    run(&[TEN, "-c", "-0"], "tests/expected/ten.txt.out")
+}
+
+#[test]
+fn ten_n_minus3_stdin() -> TestResult {
+    run_stdin(&["-n", "-3"], TEN, "tests/expected/ten.txt.n-3.out")
+}
+
+#[test]
+fn ten_c_minus5_stdin() -> TestResult {
+    run_stdin(&["-c", "-5"], TEN, "tests/expected/ten.txt.c-5.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn multiple_files() -> TestResult {
@@ -419,3 +538,69 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+#[test]
+fn multiple_files_skips_bad_c2() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read("tests/expected/bad_one_two.c2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2", &bad, ONE, TWO])
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(bad))
+        .stdout(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_with_suffix() -> TestResult {
+    let input = "0123456789".repeat(300);
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2KB"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[..2000].to_string());
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-1K"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[..3000 - 1024].to_string());
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_with_suffix() -> TestResult {
+    let input: String = (1..=1500).map(|n| format!("{}\n", n)).collect();
+    let expected: String = (1..=1024).map(|n| format!("{}\n", n)).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1KiB"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_c_huge() -> TestResult {
+    run(&[TEN, "-c", "50G"], "tests/expected/ten.txt.c50G.out")
+}
+
+#[test]
+fn ten_c_huge_stdin() -> TestResult {
+    run_stdin(&["-c", "50G"], TEN, "tests/expected/ten.txt.c50G.out")
+}
+
+#[test]
+fn one_ten_c_huge() -> TestResult {
+    run(&["-c", "50G", ONE, TEN], "tests/expected/one_ten.c50G.out")
+}
@@ -0,0 +1,290 @@
+use crate::{Config, MyResult};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+// Rough bookkeeping cost of one entry on top of the bytes it holds.
+const ENTRY_OVERHEAD: usize = 48;
+
+// Most runs that are open at once while merging.
+const MERGE_WIDTH: usize = 64;
+
+struct Record {
+    key: Vec<u8>,
+    seq: u64,
+    printed: bool,
+    line: Vec<u8>,
+}
+
+impl Record {
+    fn size(&self) -> usize {
+        self.key.len() + self.line.len() + ENTRY_OVERHEAD
+    }
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    w.write_all(bytes)
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; read_u64(r)? as usize];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_record(w: &mut impl Write, rec: &Record) -> io::Result<()> {
+    write_bytes(w, &rec.key)?;
+    w.write_all(&rec.seq.to_le_bytes())?;
+    w.write_all(&[rec.printed as u8])?;
+    write_bytes(w, &rec.line)
+}
+
+fn read_record(r: &mut impl BufRead) -> io::Result<Option<Record>> {
+    if r.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let key = read_bytes(r)?;
+    let seq = read_u64(r)?;
+    let mut printed = [0u8];
+    r.read_exact(&mut printed)?;
+    let line = read_bytes(r)?;
+    Ok(Some(Record {
+        key,
+        seq,
+        printed: printed[0] != 0,
+        line,
+    }))
+}
+
+fn order(a: &Record, b: &Record, by_seq: bool) -> Ordering {
+    if by_seq {
+        a.seq.cmp(&b.seq)
+    } else {
+        (&a.key, a.seq).cmp(&(&b.key, b.seq))
+    }
+}
+
+// Sorts the buffered records and writes them to an anonymous temporary
+// file, leaving the buffer empty.
+fn spill(records: &mut Vec<Record>, by_seq: bool) -> MyResult<File> {
+    records.sort_unstable_by(|a, b| order(a, b, by_seq));
+    let mut file = tempfile::tempfile()?;
+    {
+        let mut w = BufWriter::new(&mut file);
+        for rec in records.drain(..) {
+            write_record(&mut w, &rec)?;
+        }
+        w.flush()?;
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// The next record of one run, ordered so that the heap yields the
+// smallest record first.
+struct Head {
+    rec: Record,
+    run: usize,
+    by_seq: bool,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        order(&other.rec, &self.rec, self.by_seq)
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+// Merges sorted runs, handing every record to `f` in order.
+fn merge_runs(
+    runs: Vec<File>,
+    by_seq: bool,
+    mut f: impl FnMut(Record) -> MyResult<()>,
+) -> MyResult<()> {
+    let mut readers: Vec<_> = runs.into_iter().map(BufReader::new).collect();
+    let mut heap = BinaryHeap::new();
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(rec) = read_record(reader)? {
+            heap.push(Head { rec, run, by_seq });
+        }
+    }
+    while let Some(Head { rec, run, .. }) = heap.pop() {
+        if let Some(next) = read_record(&mut readers[run])? {
+            heap.push(Head {
+                rec: next,
+                run,
+                by_seq,
+            });
+        }
+        f(rec)?;
+    }
+    Ok(())
+}
+
+// Merges a batch of sorted runs into a single new run.
+fn merge_to_file(runs: Vec<File>, by_seq: bool) -> MyResult<File> {
+    let mut file = tempfile::tempfile()?;
+    {
+        let mut w = BufWriter::new(&mut file);
+        merge_runs(runs, by_seq, |rec| Ok(write_record(&mut w, &rec)?))?;
+        w.flush()?;
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// Sorted runs on disk. Every `MERGE_WIDTH` runs of one level are merged
+// into a single run of the next as they arrive, so the number of open
+// files stays small however much is spilled.
+struct Runs {
+    levels: Vec<Vec<File>>,
+    by_seq: bool,
+}
+
+impl Runs {
+    fn new(by_seq: bool) -> Self {
+        Runs {
+            levels: vec![],
+            by_seq,
+        }
+    }
+
+    fn push(&mut self, mut file: File) -> MyResult<()> {
+        for level in 0.. {
+            if self.levels.len() == level {
+                self.levels.push(vec![]);
+            }
+            self.levels[level].push(file);
+            if self.levels[level].len() < MERGE_WIDTH {
+                break;
+            }
+            file = merge_to_file(std::mem::take(&mut self.levels[level]), self.by_seq)?;
+        }
+        Ok(())
+    }
+
+    // Hands every record to `f` in order, first combining runs
+    // `MERGE_WIDTH` at a time until few enough are left.
+    fn merge(self, f: impl FnMut(Record) -> MyResult<()>) -> MyResult<()> {
+        let mut runs: Vec<File> = self.levels.into_iter().flatten().collect();
+        while runs.len() > MERGE_WIDTH {
+            let batch = runs.drain(..MERGE_WIDTH).collect();
+            runs.push(merge_to_file(batch, self.by_seq)?);
+        }
+        merge_runs(runs, self.by_seq, f)
+    }
+}
+
+// Prints the first occurrence of every line, wherever its duplicates are.
+// Lines are printed as soon as they are seen until the set of seen keys
+// outgrows `--max-memory`; after that the set is written out and the rest
+// of the input goes through sorted runs on disk, which are merged to drop
+// repeats and then merged again in input order.
+pub fn dedup(config: &Config, mut file: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    let budget = config.max_memory.unwrap_or(usize::MAX);
+    let mut seen = HashSet::new();
+    let mut used = 0;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        let key = config.key(&line);
+        if seen.contains(&key) {
+            continue;
+        }
+        out.write_all(&line)?;
+        used += key.len() + ENTRY_OVERHEAD;
+        seen.insert(key);
+        if used > budget {
+            break;
+        }
+    }
+
+    // Keys already printed sort ahead of any later line with the same key.
+    let mut records: Vec<Record> = std::mem::take(&mut seen)
+        .into_iter()
+        .map(|key| Record {
+            key,
+            seq: 0,
+            printed: true,
+            line: Vec::new(),
+        })
+        .collect();
+    let mut runs = Runs::new(false);
+    runs.push(spill(&mut records, false)?)?;
+    let mut seq = 0;
+    used = 0;
+    loop {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        seq += 1;
+        let rec = Record {
+            key: config.key(&line),
+            seq,
+            printed: false,
+            line: line.clone(),
+        };
+        used += rec.size();
+        records.push(rec);
+        if used > budget {
+            runs.push(spill(&mut records, false)?)?;
+            used = 0;
+        }
+    }
+    if !records.is_empty() {
+        runs.push(spill(&mut records, false)?)?;
+    }
+
+    let mut firsts = Runs::new(true);
+    let mut last_key: Option<Vec<u8>> = None;
+    used = 0;
+    runs.merge(|rec| {
+        if last_key.as_ref() == Some(&rec.key) {
+            return Ok(());
+        }
+        last_key = Some(rec.key.clone());
+        if !rec.printed {
+            used += rec.size();
+            records.push(rec);
+            if used > budget {
+                firsts.push(spill(&mut records, true)?)?;
+                used = 0;
+            }
+        }
+        Ok(())
+    })?;
+    if !records.is_empty() {
+        firsts.push(spill(&mut records, true)?)?;
+    }
+
+    firsts.merge(|rec| {
+        out.write_all(&rec.line)?;
+        Ok(())
+    })
+}
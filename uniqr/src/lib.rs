@@ -1,15 +1,65 @@
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+mod global;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+/// Options for `run` and `uniq`, normally filled in by `get_args`.
 #[derive(Debug)]
 pub struct Config {
-    in_file: String,
-    out_file: Option<String>,
-    count: bool,
+    pub in_file: String,
+    pub out_file: Option<String>,
+    pub count: bool,
+    pub repeated: bool,
+    pub all_repeated: Option<Delimit>,
+    pub group: Option<Delimit>,
+    pub unique: bool,
+    pub insensitive: bool,
+    pub ignore_trailing_space: bool,
+    pub skip_fields: usize,
+    pub skip_chars: usize,
+    pub check_chars: Option<usize>,
+    pub global: bool,
+    pub max_memory: Option<usize>,
+    pub top: Option<usize>,
+    pub min_count: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            in_file: "-".to_string(),
+            out_file: None,
+            count: false,
+            repeated: false,
+            all_repeated: None,
+            group: None,
+            unique: false,
+            insensitive: false,
+            ignore_trailing_space: false,
+            skip_fields: 0,
+            skip_chars: 0,
+            check_chars: None,
+            global: false,
+            max_memory: None,
+            top: None,
+            min_count: None,
+        }
+    }
+}
+
+// Where empty lines go when printing whole groups with `-D` or `--group`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delimit {
+    None,
+    Prepend,
+    Separate,
+    Append,
+    Both,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -35,15 +85,193 @@ pub fn get_args() -> MyResult<Config> {
                 .long("count")
                 .takes_value(false)
             )
+        .arg(
+            Arg::with_name("repeated")
+                .short("d")
+                .long("repeated")
+                .help("Only print duplicate lines, one for each group")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("all_repeated")
+                .short("D")
+                .long("all-repeated")
+                .value_name("METHOD")
+                .help("Print all duplicate lines")
+                .possible_values(&["none", "prepend", "separate"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with("count")
+            )
+        .arg(
+            Arg::with_name("group")
+                .long("group")
+                .value_name("METHOD")
+                .help("Show all items, separating groups with an empty line")
+                .possible_values(&["separate", "prepend", "append", "both"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&["count", "repeated", "all_repeated", "unique"])
+            )
+        .arg(
+            Arg::with_name("unique")
+                .short("u")
+                .long("unique")
+                .help("Only print unique lines")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("insensitive")
+                .short("i")
+                .long("ignore-case")
+                .help("Ignore differences in case when comparing")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("ignore_trailing_space")
+                .long("ignore-trailing-space")
+                .help("Ignore trailing whitespace when comparing")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("skip_fields")
+                .short("f")
+                .long("skip-fields")
+                .value_name("N")
+                .help("Avoid comparing the first N fields")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("skip_chars")
+                .short("s")
+                .long("skip-chars")
+                .value_name("N")
+                .help("Avoid comparing the first N characters")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("check_chars")
+                .short("w")
+                .long("check-chars")
+                .value_name("N")
+                .help("Compare no more than N characters in lines")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("global")
+                .long("global")
+                .help("Remove duplicates anywhere in the input, keeping the first")
+                .takes_value(false)
+                .conflicts_with_all(&["count", "repeated", "all_repeated", "group", "unique"])
+            )
+        .arg(
+            Arg::with_name("max_memory")
+                .long("max-memory")
+                .value_name("SIZE")
+                .help("Spill to temporary files beyond SIZE bytes (K, M, G suffixes)")
+                .takes_value(true)
+                .requires("global")
+            )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("N")
+                .help("Print the N most frequent lines with their counts")
+                .takes_value(true)
+                .conflicts_with_all(&["repeated", "all_repeated", "group", "unique", "global"])
+            )
+        .arg(
+            Arg::with_name("min_count")
+                .long("min-count")
+                .value_name("N")
+                .help("Only report lines seen at least N times")
+                .takes_value(true)
+                .conflicts_with_all(&["repeated", "all_repeated", "group", "unique", "global"])
+            )
         .get_matches();
 
     Ok(Config {
         in_file: matches.value_of_lossy("in_file").unwrap().to_string(),
         out_file: matches.value_of_lossy("out_file").map(String::from),
         count: matches.is_present("count"),
+        repeated: matches.is_present("repeated"),
+        all_repeated: parse_delimit(&matches, "all_repeated", Delimit::None),
+        group: parse_delimit(&matches, "group", Delimit::Separate),
+        unique: matches.is_present("unique"),
+        insensitive: matches.is_present("insensitive"),
+        ignore_trailing_space: matches.is_present("ignore_trailing_space"),
+        skip_fields: matches
+            .value_of("skip_fields")
+            .map(parse_int)
+            .transpose()
+            .map_err(|e| format!("invalid number of fields to skip -- {}", e))?
+            .unwrap_or(0),
+        skip_chars: matches
+            .value_of("skip_chars")
+            .map(parse_int)
+            .transpose()
+            .map_err(|e| format!("invalid number of bytes to skip -- {}", e))?
+            .unwrap_or(0),
+        check_chars: matches
+            .value_of("check_chars")
+            .map(parse_int)
+            .transpose()
+            .map_err(|e| format!("invalid number of bytes to compare -- {}", e))?,
+        global: matches.is_present("global"),
+        max_memory: matches
+            .value_of("max_memory")
+            .map(parse_size)
+            .transpose()
+            .map_err(|e| format!("invalid memory size -- {}", e))?,
+        top: matches
+            .value_of("top")
+            .map(parse_int)
+            .transpose()
+            .map_err(|e| format!("invalid number of lines -- {}", e))?,
+        min_count: matches
+            .value_of("min_count")
+            .map(parse_int)
+            .transpose()
+            .map_err(|e| format!("invalid count -- {}", e))?,
     })
 }
 
+fn parse_delimit(matches: &ArgMatches, name: &str, default: Delimit) -> Option<Delimit> {
+    if !matches.is_present(name) {
+        return None;
+    }
+    Some(match matches.value_of(name) {
+        Some("none") => Delimit::None,
+        Some("prepend") => Delimit::Prepend,
+        Some("separate") => Delimit::Separate,
+        Some("append") => Delimit::Append,
+        Some("both") => Delimit::Both,
+        _ => default,
+    })
+}
+
+fn parse_int(val: &str) -> MyResult<usize> {
+    val.parse().map_err(|_| From::from(val))
+}
+
+fn parse_size(val: &str) -> MyResult<usize> {
+    let (num, unit) = match val.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => val.split_at(i),
+        None => (val, ""),
+    };
+    let multiplier: usize = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(From::from(val)),
+    };
+    num.parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| From::from(val))
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -51,55 +279,315 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
+impl Config {
+    /// The bytes of a line that take part in the comparison: everything but
+    /// the newline, after skipping fields and bytes and applying the width
+    /// limit. A carriage return is compared like any other byte unless
+    /// `--ignore-trailing-space` is given.
+    pub fn key(&self, line: &[u8]) -> Vec<u8> {
+        let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+        let mut rest = line.strip_suffix(b"\n").unwrap_or(line);
+        if self.ignore_trailing_space {
+            while let [init @ .., last] = rest {
+                if !last.is_ascii_whitespace() {
+                    break;
+                }
+                rest = init;
+            }
+        }
+        for _ in 0..self.skip_fields {
+            let start = rest.iter().position(|b| !is_blank(b)).unwrap_or(rest.len());
+            rest = &rest[start..];
+            let end = rest.iter().position(is_blank).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+        rest = &rest[self.skip_chars.min(rest.len())..];
+        if let Some(width) = self.check_chars {
+            rest = &rest[..width.min(rest.len())];
+        }
+        if !self.insensitive {
+            return rest.to_vec();
+        }
+        match std::str::from_utf8(rest) {
+            Ok(s) => s.to_lowercase().into_bytes(),
+            Err(_) => rest.to_ascii_lowercase(),
+        }
+    }
+}
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut file = open(&config.in_file)
-        .map_err(|e| format!("{}: {}", config.in_file, e))?;
-    let mut line = String::new();
-    let mut prev_line = String::new();
+/// Groups adjacent lines with equal keys, yielding the size of each group
+/// and its first line, newline included.
+pub struct Groups<R, F> {
+    reader: R,
+    key: F,
+    buf: Vec<u8>,
+    pending: Option<(Vec<u8>, Vec<u8>)>,
+    done: bool,
+}
+
+fn whole_line(line: &[u8]) -> Vec<u8> {
+    line.strip_suffix(b"\n").unwrap_or(line).to_vec()
+}
+
+impl<R: BufRead> Groups<R, fn(&[u8]) -> Vec<u8>> {
+    /// Compares whole lines, ignoring only the newline.
+    pub fn new(reader: R) -> Self {
+        Groups::with_key(reader, whole_line)
+    }
+}
+
+impl<R: BufRead, F: FnMut(&[u8]) -> Vec<u8>> Groups<R, F> {
+    /// Compares lines by the bytes `key` extracts from them, such as
+    /// `|line| config.key(line)`.
+    pub fn with_key(reader: R, key: F) -> Self {
+        Groups {
+            reader,
+            key,
+            buf: Vec::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    // Reads the next line, returning its key; the line is left in `buf`.
+    fn read(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some((self.key)(&self.buf)))
+    }
+}
+
+impl<R: BufRead, F: FnMut(&[u8]) -> Vec<u8>> Iterator for Groups<R, F> {
+    type Item = io::Result<(usize, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (key, line) = match self.pending.take() {
+            Some(pending) => pending,
+            None => match self.read() {
+                Ok(Some(key)) => (key, std::mem::take(&mut self.buf)),
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            },
+        };
+
+        let mut count = 1;
+        loop {
+            match self.read() {
+                Ok(Some(next_key)) if next_key == key => count += 1,
+                Ok(Some(next_key)) => {
+                    self.pending = Some((next_key, std::mem::take(&mut self.buf)));
+                    break;
+                }
+                Ok(None) => {
+                    self.done = true;
+                    break;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        Some(Ok((count, line)))
+    }
+}
+
+// Counts every distinct line in the whole input and prints them most
+// frequent first, ties in order of first appearance.
+fn frequencies(config: &Config, mut file: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    let mut counts: HashMap<Vec<u8>, (usize, usize, Vec<u8>)> = HashMap::new();
+    let mut line = Vec::new();
+    let mut seq = 0;
+    loop {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        counts
+            .entry(config.key(&line))
+            .or_insert_with(|| {
+                seq += 1;
+                (0, seq, line.clone())
+            })
+            .0 += 1;
+    }
+
+    let min_count = config.min_count.unwrap_or(0);
+    let mut entries: Vec<_> = counts
+        .into_values()
+        .filter(|(count, _, _)| *count >= min_count)
+        .collect();
+    entries.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    entries.truncate(config.top.unwrap_or(usize::MAX));
+
+    for (count, _, line) in entries {
+        write!(out, "{:>4} ", count)?;
+        out.write_all(&line)?;
+        if !line.ends_with(b"\n") {
+            out.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+fn create(filename: &Option<String>) -> MyResult<Box<dyn Write>> {
+    match filename {
+        None => Ok(Box::new(BufWriter::new(io::stdout()))),
+        Some(name) => Ok(Box::new(BufWriter::new(
+            File::create(name).map_err(|e| format!("{}: {}", name, e))?,
+        ))),
+    }
+}
+
+// Prints every line of the groups selected by `-D` or `--group`, with
+// empty lines between groups as requested.
+fn print_all(config: &Config, mut input: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    let mut line = Vec::new();
+    let mut prev_line = Vec::new();
+    let mut prev_key = Vec::new();
     let mut count = 0;
-    let mut output = String::new();
+    let mut groups_printed = 0;
     loop {
-        let bytes = file.read_line(&mut line)?;
+        let bytes = input.read_until(b'\n', &mut line)?;
 
         if bytes == 0 {
             break;
         }
 
-        if prev_line.trim() != line.trim() {
-            if count > 0 {
-                if config.count {
-                    output.push_str(&format!("{:>4} {}", count, prev_line));
-                } else{
-                    output.push_str(&format!("{}", prev_line));
+        let key = config.key(&line);
+        if count > 0 && key == prev_key {
+            count += 1;
+            if let Some(delimit) = config.all_repeated {
+                // Every line printed by `-D` is a duplicate, so `-u`
+                // leaves nothing.
+                if !config.unique {
+                    // The first line of a duplicate group is held back
+                    // until a second one shows that it is a duplicate.
+                    if count == 2 {
+                        if delimit == Delimit::Prepend
+                            || (delimit == Delimit::Separate && groups_printed > 0)
+                        {
+                            out.write_all(b"\n")?;
+                        }
+                        groups_printed += 1;
+                        out.write_all(&prev_line)?;
+                    }
+                    out.write_all(&line)?;
                 }
-            } 
-            prev_line = line.clone();
-            count = 0;
-        } 
-        
-        count += 1;
-        line.clear();
-    }
-    if count > 0 {
-        if config.count {
-            output.push_str(&format!("{:>4} {}", count, prev_line));
+            } else {
+                out.write_all(&line)?;
+            }
         } else {
-            output.push_str(&format!("{}", prev_line));
+            if let Some(delimit) = config.group {
+                if delimit == Delimit::Prepend
+                    || delimit == Delimit::Both
+                    || (groups_printed > 0
+                        && (delimit == Delimit::Separate || delimit == Delimit::Append))
+                {
+                    out.write_all(b"\n")?;
+                }
+                groups_printed += 1;
+                out.write_all(&line)?;
+            }
+            std::mem::swap(&mut prev_line, &mut line);
+            prev_key = key;
+            count = 1;
         }
+        line.clear();
     }
-    match config.out_file {
-        None => print!("{}", output),
-        Some(filename) => {
-            let mut f = File::create(filename)?;
-            let _res = f.write(output.as_bytes());
-        },
+    if let Some(Delimit::Append | Delimit::Both) = config.group {
+        if groups_printed > 0 {
+            out.write_all(b"\n")?;
+        }
     }
     Ok(())
 }
 
+/// Reads `input` and writes the result to `out` according to `config`,
+/// ignoring `in_file` and `out_file`.
+pub fn uniq(config: &Config, input: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    if config.global {
+        return global::dedup(config, input, out);
+    }
+    if config.top.is_some() || config.min_count.is_some() {
+        return frequencies(config, input, out);
+    }
+    if config.all_repeated.is_some() || config.group.is_some() {
+        return print_all(config, input, out);
+    }
+
+    for group in Groups::with_key(input, |line| config.key(line)) {
+        let (count, line) = group?;
+        if (config.repeated && count == 1) || (config.unique && count > 1) {
+            continue;
+        }
+        if config.count {
+            write!(out, "{:>4} ", count)?;
+        }
+        out.write_all(&line)?;
+    }
+    Ok(())
+}
 
+pub fn run(config: Config) -> MyResult<()> {
+    let file = open(&config.in_file)
+        .map_err(|e| format!("{}: {}", config.in_file, e))?;
+    let mut out = create(&config.out_file)?;
+    uniq(&config, file, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
 
+#[cfg(test)]
+mod tests {
+    use super::{Config, Groups};
+    use std::io::Cursor;
 
+    #[test]
+    fn test_groups() {
+        let text = b"a\na\nb\r\nb\na";
+        let groups: Vec<_> = Groups::new(Cursor::new(text))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            groups,
+            vec![
+                (2, b"a\n".to_vec()),
+                (1, b"b\r\n".to_vec()),
+                (1, b"b\n".to_vec()),
+                (1, b"a".to_vec()),
+            ]
+        );
 
+        let groups: Vec<_> = Groups::new(Cursor::new(b"")).collect();
+        assert!(groups.is_empty());
+    }
 
+    #[test]
+    fn test_groups_with_key() {
+        let config = Config {
+            skip_fields: 1,
+            insensitive: true,
+            ..Config::default()
+        };
+        let text = "1 foo\n2 FOO\n3 bar\n";
+        let groups: Vec<_> = Groups::with_key(Cursor::new(text), |line| config.key(line))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            groups,
+            vec![(2, b"1 foo\n".to_vec()), (1, b"3 bar\n".to_vec())]
+        );
+    }
+}
@@ -13,6 +13,9 @@ struct Test {
 }
 
 const PRG: &str = "uniqr";
+const LOG: &str = "tests/inputs/log.txt";
+const CRLF: &str = "tests/inputs/crlf.txt";
+const SCATTERED: &str = "tests/inputs/scattered.txt";
 
 const EMPTY: Test = Test {
     input: "tests/inputs/empty.txt",
@@ -605,3 +608,217 @@ fn t6_outfile_count() -> TestResult {
 fn t6_stdin_outfile_count() -> TestResult {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+fn run_args(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn three_repeated() -> TestResult {
+    run_args(&[THREE.input, "-d"], "tests/expected/three.txt.d.out")
+}
+
+#[test]
+fn three_unique() -> TestResult {
+    run_args(&[THREE.input, "-u"], "tests/expected/three.txt.u.out")
+}
+
+#[test]
+fn three_all_repeated() -> TestResult {
+    run_args(&[THREE.input, "-D"], "tests/expected/three.txt.D.out")
+}
+
+#[test]
+fn three_all_repeated_separate() -> TestResult {
+    run_args(
+        &[THREE.input, "--all-repeated=separate"],
+        "tests/expected/three.txt.D_separate.out",
+    )
+}
+
+#[test]
+fn three_all_repeated_unique() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "-D", "-u"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn three_group() -> TestResult {
+    run_args(&[THREE.input, "--group"], "tests/expected/three.txt.group.out")
+}
+
+#[test]
+fn three_group_both() -> TestResult {
+    run_args(
+        &[THREE.input, "--group=both"],
+        "tests/expected/three.txt.group_both.out",
+    )
+}
+
+#[test]
+fn dies_group_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "--group", "-c"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn log_skip_fields() -> TestResult {
+    run_args(&[LOG, "-f", "1"], "tests/expected/log.txt.f1.out")
+}
+
+#[test]
+fn log_skip_fields_insensitive_count() -> TestResult {
+    run_args(
+        &[LOG, "-f", "1", "-i", "-c"],
+        "tests/expected/log.txt.f1.i.c.out",
+    )
+}
+
+#[test]
+fn log_skip_chars_check_chars_repeated() -> TestResult {
+    run_args(
+        &[LOG, "-s", "9", "-w", "4", "-d"],
+        "tests/expected/log.txt.s9.w4.d.out",
+    )
+}
+
+#[test]
+fn dies_bad_skip_fields() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([LOG, "-f", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of fields to skip -- x",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf() -> TestResult {
+    run_args(&[CRLF], "tests/expected/crlf.txt.out")
+}
+
+#[test]
+fn crlf_count() -> TestResult {
+    run_args(&[CRLF, "-c"], "tests/expected/crlf.txt.c.out")
+}
+
+#[test]
+fn crlf_ignore_trailing_space_count() -> TestResult {
+    run_args(
+        &[CRLF, "--ignore-trailing-space", "-c"],
+        "tests/expected/crlf.txt.trailing.c.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn scattered_global() -> TestResult {
+    run_args(
+        &[SCATTERED, "--global"],
+        "tests/expected/scattered.txt.global.out",
+    )
+}
+
+#[test]
+fn scattered_global_spill() -> TestResult {
+    run_args(
+        &[SCATTERED, "--global", "--max-memory", "1"],
+        "tests/expected/scattered.txt.global.out",
+    )
+}
+
+#[test]
+fn scattered_global_insensitive_spill() -> TestResult {
+    run_args(
+        &[SCATTERED, "--global", "-i", "--max-memory", "100"],
+        "tests/expected/scattered.txt.global.i.out",
+    )
+}
+
+#[test]
+fn global_many_runs() -> TestResult {
+    // Enough spilled runs to need several merge passes.
+    let input: String = (1..=20000).map(|i| format!("{}\n", i % 5000)).collect();
+    let expected: String = (1..=5000).map(|i| format!("{}\n", i % 5000)).collect();
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory", "1K"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn dies_bad_max_memory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([SCATTERED, "--global", "--max-memory", "5X"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid memory size -- 5X"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn three_top() -> TestResult {
+    run_args(&[THREE.input, "--top", "3"], "tests/expected/three.txt.top3.out")
+}
+
+#[test]
+fn scattered_min_count() -> TestResult {
+    run_args(
+        &[SCATTERED, "--min-count", "2"],
+        "tests/expected/scattered.txt.min2.out",
+    )
+}
+
+#[test]
+fn scattered_top_insensitive_min_count() -> TestResult {
+    run_args(
+        &[SCATTERED, "--top", "2", "-i", "--min-count", "2"],
+        "tests/expected/scattered.txt.top2.i.min2.out",
+    )
+}
+
+#[test]
+fn dies_top_unique() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "--top", "3", "-u"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn dies_out_file_full() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "/dev/full"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No space left on device"));
+    Ok(())
+}
@@ -1,76 +1,237 @@
 use clap::{App, Arg};
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File, Metadata};
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use unicode_width::UnicodeWidthChar;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    files: Vec<String>,
+    files: Vec<PathBuf>,
+    files0_from: Option<String>,
+    print_names: bool,
     lines: bool,
     words: bool,
     bytes: bool,
     chars: bool,
+    max_line_length: bool,
+    stats: bool,
+    format: OutputFormat,
+    total: TotalMode,
 }
 
 #[derive(Debug, PartialEq)]
+pub enum OutputFormat {
+    Columns,
+    Json,
+    Csv,
+    Tsv,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TotalMode {
+    Auto,
+    Always,
+    Only,
+    Never,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    max_line_length: usize,
+    stats: LineStats,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{count, FileInfo};
-    use std::io::Cursor;
+// Per-line figures for --stats. A final line without a newline is included
+// here even though it is not counted in `num_lines`.
+#[derive(Debug, Default, PartialEq)]
+pub struct LineStats {
+    longest_line: usize,
+    num_blank: usize,
+    num_lines: usize,
+    total_length: usize,
+}
 
-    #[test]
-    fn test_count() {
-        let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
-        assert!(info.is_ok());
-        let expected = FileInfo {
-            num_lines: 1,
-            num_words: 10,
-            num_chars: 48,
-            num_bytes: 48,
-        };
-        assert_eq!(info.unwrap(), expected);
+impl LineStats {
+    fn average(&self) -> f64 {
+        if self.num_lines == 0 {
+            0.0
+        } else {
+            self.total_length as f64 / self.num_lines as f64
+        }
+    }
+}
+
+impl FileInfo {
+    // Adds another file's counts as if it followed this one, so the longest
+    // line of a total is numbered across all files.
+    fn add(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        if other.max_line_length > self.max_line_length || self.stats.num_lines == 0 {
+            self.max_line_length = self.max_line_length.max(other.max_line_length);
+            if other.stats.longest_line > 0 {
+                self.stats.longest_line = self.stats.num_lines + other.stats.longest_line;
+            }
+        }
+        self.stats.num_blank += other.stats.num_blank;
+        self.stats.num_lines += other.stats.num_lines;
+        self.stats.total_length += other.stats.total_length;
     }
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
+// Bytes read from each file at a time.
+const CHUNK_SIZE: usize = 256 * 1024;
 
+// Word, character and line-width state carried from one chunk to the next.
+#[derive(Default)]
+struct Counter {
+    num_words: usize,
+    num_chars: usize,
+    in_word: bool,
+    max_line_length: usize,
+    stats: LineStats,
+    line_pos: usize,
+    line_width: usize,
+    line_started: bool,
+    line_has_word: bool,
+}
+
+impl Counter {
+    // Widths follow GNU wc -L: tabs stop every eight columns, carriage
+    // returns and form feeds start over at column zero, and wide characters
+    // take two columns. Control characters neither start nor end a word, the
+    // same as GNU wc in a UTF-8 locale.
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.num_chars += 1;
+            self.line_started = true;
+            match c {
+                '\n' => self.end_line(),
+                '\r' | '\x0c' => {
+                    self.end_segment();
+                    self.in_word = false;
+                }
+                '\t' => {
+                    self.line_pos += 8 - self.line_pos % 8;
+                    self.in_word = false;
+                }
+                c if c.is_whitespace() => {
+                    self.line_pos += c.width().unwrap_or(0);
+                    self.in_word = false;
+                }
+                c if c.is_control() => {}
+                c => {
+                    self.line_pos += c.width().unwrap_or(0);
+                    self.start_word();
+                }
+            }
+        }
+    }
+
+    fn end_segment(&mut self) {
+        self.line_width = self.line_width.max(self.line_pos);
+        self.line_pos = 0;
+    }
+
+    fn end_line(&mut self) {
+        self.end_segment();
+        self.stats.num_lines += 1;
+        self.stats.total_length += self.line_width;
+        if self.line_width > self.max_line_length || self.stats.num_lines == 1 {
+            self.max_line_length = self.max_line_length.max(self.line_width);
+            self.stats.longest_line = self.stats.num_lines;
+        }
+        if !self.line_has_word {
+            self.stats.num_blank += 1;
+        }
+        self.in_word = false;
+        self.line_width = 0;
+        self.line_started = false;
+        self.line_has_word = false;
+    }
+
+    fn start_word(&mut self) {
+        self.line_has_word = true;
+        if !self.in_word {
+            self.in_word = true;
+            self.num_words += 1;
+        }
+    }
+
+    // Counts the UTF-8 text in `buf`, skipping invalid bytes, and returns
+    // the length of a trailing sequence that may be completed by the next
+    // chunk.
+    fn bytes(&mut self, mut buf: &[u8]) -> usize {
+        loop {
+            match std::str::from_utf8(buf) {
+                Ok(text) => {
+                    self.text(text);
+                    return 0;
+                }
+                Err(err) => {
+                    let (valid, rest) = buf.split_at(err.valid_up_to());
+                    self.text(std::str::from_utf8(valid).unwrap());
+                    match err.error_len() {
+                        None => return rest.len(),
+                        Some(len) => buf = &rest[len..],
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Words, characters and line widths need the text decoded; without them
+// only newlines are looked for.
+pub fn count(mut file: impl Read, decode: bool) -> MyResult<FileInfo> {
     let mut num_lines = 0;
-    let mut num_words = 0;
     let mut num_bytes = 0;
-    let mut num_chars = 0; 
-    let mut string = String::new();
+    let mut counter = Counter::default();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut carry = 0;
 
-    while let Ok(res) = file.read_line(&mut string){
-        if res > 0 && (!string.eq("\n") || !string.eq("\r\n")) {
-            num_lines += 1;
-            num_words += string.split_whitespace().count();
-            num_bytes += res; 
-            num_chars += string.chars().count();
-            string.clear();
-        } else{
-            break;
+    loop {
+        let n = match file.read(&mut buf[carry..]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let end = carry + n;
+        num_lines += memchr::memchr_iter(b'\n', &buf[carry..end]).count();
+        num_bytes += n;
+        if decode {
+            carry = counter.bytes(&buf[..end]);
+            buf.copy_within(end - carry..end, 0);
         }
     }
 
+    if counter.line_started {
+        counter.end_line();
+    }
+
     Ok(FileInfo {
         num_lines,
-        num_words,
+        num_words: counter.num_words,
         num_bytes,
-        num_chars,
+        num_chars: counter.num_chars,
+        max_line_length: counter.max_line_length,
+        stats: counter.stats,
     })
 }
 
-
 pub fn get_args() -> MyResult<Config> {
     let matches = App::new("wcr")
         .version("0.1.0")
@@ -83,6 +244,14 @@ pub fn get_args() -> MyResult<Config> {
                 .multiple(true)
                 .default_value("-")
             )
+        .arg(
+            Arg::with_name("files0_from")
+                .value_name("F")
+                .help("Read input file names from F, separated by NULs; - means stdin")
+                .long("files0-from")
+                .takes_value(true)
+                .conflicts_with("files")
+            )
         .arg(
             Arg::with_name("lines")
                 .value_name("LINES")
@@ -106,7 +275,6 @@ pub fn get_args() -> MyResult<Config> {
                 .short("c")
                 .long("bytes")
                 .takes_value(false)
-                .conflicts_with("chars")
             )
         .arg(
             Arg::with_name("chars")
@@ -116,87 +284,531 @@ pub fn get_args() -> MyResult<Config> {
                 .long("chars")
                 .takes_value(false)
                 .conflicts_with("bytes")
+            )
+        .arg(
+            Arg::with_name("max_line_length")
+                .help("Print the display width of the longest line")
+                .short("L")
+                .long("max-line-length")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("stats")
+                .help("Also print the longest line's number, blank lines and average line width")
+                .long("stats")
+                .takes_value(false)
+            )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output layout")
+                .possible_values(&["columns", "json", "csv", "tsv"])
+                .default_value("columns")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("total")
+                .long("total")
+                .value_name("WHEN")
+                .help("When to print a line with total counts")
+                .possible_values(&["auto", "always", "only", "never"])
+                .default_value("auto")
+                .takes_value(true)
             ).get_matches();
 
     let mut lines = matches.is_present("lines");
     let mut words = matches.is_present("words");
     let mut bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let max_line_length = matches.is_present("max_line_length");
+
+    let format = match matches.value_of("format") {
+        Some("json") => OutputFormat::Json,
+        Some("csv") => OutputFormat::Csv,
+        Some("tsv") => OutputFormat::Tsv,
+        _ => OutputFormat::Columns,
+    };
 
-    if !lines && !words && !bytes && !chars {
+    let total = match matches.value_of("total") {
+        Some("always") => TotalMode::Always,
+        Some("only") => TotalMode::Only,
+        Some("never") => TotalMode::Never,
+        _ => TotalMode::Auto,
+    };
+
+    if !lines && !words && !bytes && !chars && !max_line_length {
         lines = true;
         words = true;
         bytes = true;
     }
 
+    let files0_from = matches.value_of("files0_from").map(String::from);
+    let files = match &files0_from {
+        Some(list) => read_names(list)?,
+        None => matches.values_of_os("files").unwrap().map(PathBuf::from).collect(),
+    };
+
     Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
+        files,
+        print_names: files0_from.is_some() || matches.occurrences_of("files") > 0,
+        files0_from,
         lines,
         words,
         bytes,
         chars,
+        max_line_length,
+        stats: matches.is_present("stats"),
+        format,
+        total,
     })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+fn is_stdin(filename: &Path) -> bool {
+    filename.as_os_str() == "-"
+}
+
+fn open(filename: &Path) -> MyResult<Box<dyn Read>> {
+    if is_stdin(filename) {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(filename)?))
     }
 }
 
+// With nothing but bytes selected, a regular file's size is its count and
+// it is not read. Files reporting no size, like those under /proc, are
+// still read.
+fn count_path(config: &Config, filename: &Path) -> MyResult<FileInfo> {
+    let decode = config.words || config.chars || config.max_line_length || config.stats;
+    if !is_stdin(filename) && !decode && !config.lines {
+        let file = File::open(filename)?;
+        let metadata = file.metadata()?;
+        if metadata.is_file() && metadata.len() > 0 {
+            return Ok(FileInfo {
+                num_bytes: metadata.len() as usize,
+                ..Default::default()
+            });
+        }
+        return count(file, decode);
+    }
+    count(open(filename)?, decode)
+}
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut line_sum = 0;
-    let mut word_sum = 0;
-    let mut byte_sum = 0;
-    let mut char_sum = 0;
+// Reads a NUL-separated list of file names. Empty names are kept so they
+// can be reported in their place.
+fn read_names(list: &str) -> MyResult<Vec<PathBuf>> {
+    let mut buf = vec![];
+    open(Path::new(list))
+        .and_then(|mut file| Ok(file.read_to_end(&mut buf)?))
+        .map_err(|err| format!("cannot open {} for reading: {}", list, err))?;
+    if buf.last() == Some(&0) {
+        buf.pop();
+    }
+    if buf.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(buf.split(|&b| b == 0).map(name_from_bytes).collect())
+}
+
+// Names are taken byte for byte, so they need not be UTF-8.
+#[cfg(unix)]
+fn name_from_bytes(name: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(name))
+}
+
+#[cfg(not(unix))]
+fn name_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+fn count_file(config: &Config, i: usize) -> Result<FileInfo, String> {
+    let filename = &config.files[i];
+    if let Some(list) = &config.files0_from {
+        if filename.as_os_str().is_empty() {
+            return Err(format!("{}:{}: invalid zero-length file name", list, i + 1));
+        }
+        if list == "-" && is_stdin(filename) {
+            return Err(
+                "when reading file names from stdin, no file name of '-' allowed".to_string(),
+            );
+        }
+    }
+    count_path(config, filename).map_err(|err| format!("{}: {}", filename.display(), err))
+}
+
+// The selected counts, named as they appear in structured output.
+fn fields(config: &Config, info: &FileInfo) -> Vec<(&'static str, String)> {
+    let mut fields = vec![];
+    if config.lines {
+        fields.push(("lines", info.num_lines.to_string()));
+    }
+    if config.words {
+        fields.push(("words", info.num_words.to_string()));
+    }
+    if config.bytes {
+        fields.push(("bytes", info.num_bytes.to_string()));
+    }
+    if config.chars {
+        fields.push(("chars", info.num_chars.to_string()));
+    }
+    if config.max_line_length {
+        fields.push(("max_line_length", info.max_line_length.to_string()));
+    }
+    if config.stats {
+        fields.push(("longest_line", info.stats.longest_line.to_string()));
+        fields.push(("blank_lines", info.stats.num_blank.to_string()));
+        fields.push(("avg_line_length", format!("{:.1}", info.stats.average())));
+    }
+    fields
+}
+
+fn json_escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn tsv_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn print_header(config: &Config) {
+    let sep = match config.format {
+        OutputFormat::Csv => ",",
+        OutputFormat::Tsv => "\t",
+        _ => return,
+    };
+    let mut names = vec!["file"];
+    names.extend(fields(config, &FileInfo::default()).iter().map(|(name, _)| name));
+    println!("{}", names.join(sep));
+}
+
+#[cfg(unix)]
+fn stdin_metadata() -> io::Result<Metadata> {
+    use std::os::fd::AsFd;
+    File::from(io::stdin().as_fd().try_clone_to_owned()?).metadata()
+}
+
+#[cfg(not(unix))]
+fn stdin_metadata() -> io::Result<Metadata> {
+    Err(io::Error::from(ErrorKind::Unsupported))
+}
+
+// Column width as chosen by GNU wc: wide enough for the combined size of
+// the regular files, at least 7 when any input is a pipe or device, and
+// unpadded for a single count of a single file or a lone total.
+fn number_width(config: &Config) -> usize {
+    let num_counts = [
+        config.lines,
+        config.words,
+        config.bytes,
+        config.chars,
+        config.max_line_length,
+        config.stats,
+    ]
+    .iter()
+    .filter(|&&selected| selected)
+    .count();
+    if config.total == TotalMode::Only || (config.files.len() == 1 && num_counts == 1) {
+        return 1;
+    }
+
+    let mut min_width = 1;
+    let mut total_size = 0;
+    let stdin_listed = config.files0_from.as_deref() == Some("-");
     for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if let Ok(counts) = count(file) {
-                    if config.lines {
-                        print!("{:8}", counts.num_lines);
-                        line_sum += counts.num_lines;
-                    }
-                    if config.words {
-                        print!("{:8}", counts.num_words);
-                        word_sum += counts.num_words;
-                    }
-                    if config.bytes {
-                        print!("{:8}", counts.num_bytes);
-                        byte_sum += counts.num_bytes;
-                    }
-                    if config.chars {
-                        print!("{:8}", counts.num_chars);
-                        char_sum += counts.num_chars;
-                    }
-                    if !config.files[0].eq("-") {
-                        println!(" {}", filename);
-                    } else {
-                        println!();
-                    }
-                }
-            },
+        let metadata = if !is_stdin(filename) {
+            fs::metadata(filename)
+        } else if stdin_listed {
+            continue;
+        } else {
+            stdin_metadata()
+        };
+        match metadata {
+            Ok(metadata) if metadata.is_file() => total_size += metadata.len(),
+            Ok(_) => min_width = 7,
+            Err(_) => {}
         }
     }
-    if config.files.len() > 1 {
-        if config.lines {
-            print!("{:8}", line_sum);
+    total_size.to_string().len().max(min_width)
+}
+
+// What a line of counts is for: a file, named or not, or the total.
+enum Record<'a> {
+    Named(&'a str),
+    Unnamed,
+    Total,
+}
+
+// Structured formats mark the total with a null or empty file, which no
+// input can have, so a file called "total" is not mistaken for it.
+fn print_counts(config: &Config, info: &FileInfo, record: Record, width: usize) {
+    let fields = fields(config, info);
+    let name = match record {
+        Record::Named(name) => name,
+        Record::Unnamed => "-",
+        Record::Total => "",
+    };
+    match config.format {
+        OutputFormat::Columns => {
+            for (i, (_, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    print!(" ");
+                }
+                print!("{:>width$}", value);
+            }
+            match record {
+                Record::Named(name) => println!(" {}", name),
+                Record::Total if config.total != TotalMode::Only => println!(" total"),
+                _ => println!(),
+            }
         }
-        if config.words {
-            print!("{:8}", word_sum);
+        OutputFormat::Json => {
+            match record {
+                Record::Total => print!("{{\"file\":null"),
+                _ => print!("{{\"file\":\"{}\"", json_escape(name)),
+            }
+            for (key, value) in &fields {
+                print!(",\"{}\":{}", key, value);
+            }
+            println!("}}");
         }
-        if config.bytes {
-            print!("{:8}", byte_sum);
+        OutputFormat::Csv => {
+            print!("{}", csv_escape(name));
+            for (_, value) in &fields {
+                print!(",{}", value);
+            }
+            println!();
         }
-        if config.chars {
-            print!("{:8}", char_sum);
+        OutputFormat::Tsv => {
+            print!("{}", tsv_escape(name));
+            for (_, value) in &fields {
+                print!("\t{}", value);
+            }
+            println!();
         }
-        println!(" total")
     }
+}
+
+// Files are counted on a pool of worker threads; results are printed in
+// argument order as soon as every earlier file has been printed. Every `-`
+// reads the same stdin, so whichever worker takes the first one counts
+// them all in order, and as with GNU wc the first gets all of the input.
+pub fn run(config: Config) -> MyResult<()> {
+    let mut total = FileInfo::default();
+    let width = number_width(&config);
+    print_header(&config);
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(config.files.len());
+    let next = AtomicUsize::new(0);
+    let stdin_entries: Vec<usize> = (0..config.files.len())
+        .filter(|&i| is_stdin(&config.files[i]))
+        .collect();
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            let (tx, next, config) = (tx.clone(), &next, &config);
+            let stdin_entries = &stdin_entries;
+            s.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= config.files.len() {
+                    break;
+                }
+                let batch = match stdin_entries.first() {
+                    Some(&first) if i == first => stdin_entries.as_slice(),
+                    _ if is_stdin(&config.files[i]) => continue,
+                    _ => std::slice::from_ref(&i),
+                };
+                for &i in batch {
+                    if tx.send((i, count_file(config, i))).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(tx);
 
-    
+        let mut results: Vec<_> = config.files.iter().map(|_| None).collect();
+        let mut printed = 0;
+        for (i, result) in rx {
+            results[i] = Some(result);
+            while let Some(result) = results.get_mut(printed).and_then(Option::take) {
+                let filename = &config.files[printed];
+                printed += 1;
+                match result {
+                    Err(err) => eprintln!("{}", err),
+                    Ok(info) => {
+                        if config.total != TotalMode::Only {
+                            let name = filename.to_string_lossy();
+                            let record = if config.print_names {
+                                Record::Named(&name)
+                            } else {
+                                Record::Unnamed
+                            };
+                            print_counts(&config, &info, record, width);
+                        }
+                        total.add(&info);
+                    }
+                }
+            }
+        }
+    });
+
+    let print_total = match config.total {
+        TotalMode::Auto => config.files.len() > 1,
+        TotalMode::Always | TotalMode::Only => true,
+        TotalMode::Never => false,
+    };
+    if print_total {
+        print_counts(&config, &total, Record::Total, width);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{count, csv_escape, tsv_escape, FileInfo, LineStats};
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_count() {
+        let text = "I don't want the world. I just want your half.\r\n";
+        let info = count(Cursor::new(text), true);
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 10,
+            num_chars: 48,
+            num_bytes: 48,
+            max_line_length: 46,
+            stats: LineStats {
+                longest_line: 1,
+                num_blank: 0,
+                num_lines: 1,
+                total_length: 46,
+            },
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_no_trailing_newline() {
+        let info = count(Cursor::new("one\ntwo three"), true);
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 3,
+            num_chars: 13,
+            num_bytes: 13,
+            max_line_length: 9,
+            stats: LineStats {
+                longest_line: 2,
+                num_blank: 0,
+                num_lines: 2,
+                total_length: 12,
+            },
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let info = count(Cursor::new(b"caf\xe9 na\xc3\xafve\n\xff\x00\n".to_vec()), true);
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 2,
+            num_chars: 12,
+            num_bytes: 15,
+            max_line_length: 9,
+            stats: LineStats {
+                longest_line: 1,
+                num_blank: 1,
+                num_lines: 2,
+                total_length: 9,
+            },
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_split_char() {
+        let first = Cursor::new(b"na\xc3".to_vec());
+        let second = Cursor::new(b"\xafve \xe2\x82".to_vec());
+        let info = count(first.chain(second), true);
+        let expected = FileInfo {
+            num_lines: 0,
+            num_words: 1,
+            num_chars: 6,
+            num_bytes: 9,
+            max_line_length: 6,
+            stats: LineStats {
+                longest_line: 1,
+                num_blank: 0,
+                num_lines: 1,
+                total_length: 6,
+            },
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_line_width() {
+        let info = count(Cursor::new("ab\tc\n日本語x\n\n  \nlast"), true);
+        let expected = FileInfo {
+            num_lines: 4,
+            num_words: 4,
+            num_chars: 18,
+            num_bytes: 24,
+            max_line_length: 9,
+            stats: LineStats {
+                longest_line: 1,
+                num_blank: 2,
+                num_lines: 5,
+                total_length: 22,
+            },
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_lines_only() {
+        let info = count(Cursor::new("ab\tc\n日本語x\n\n  \nlast"), false);
+        let expected = FileInfo {
+            num_lines: 4,
+            num_bytes: 24,
+            ..Default::default()
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(csv_escape("a.txt"), "a.txt");
+        assert_eq!(csv_escape("a,\"b\".txt"), "\"a,\"\"b\"\".txt\"");
+        assert_eq!(tsv_escape("a\tb\\c\n"), "a\\tb\\\\c\\n");
+    }
+}
@@ -9,6 +9,8 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const MIXED: &str = "tests/inputs/mixed.txt";
+const WIDTHS: &str = "tests/inputs/widths.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -171,6 +173,280 @@ fn atlamal_stdin() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn mixed() -> TestResult {
+    run(&[MIXED], "tests/expected/mixed.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_bytes() -> TestResult {
+    run(&["-c", MIXED], "tests/expected/mixed.txt.c.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_chars() -> TestResult {
+    run(&["-m", MIXED], "tests/expected/mixed.txt.m.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_words() -> TestResult {
+    run(&["-w", MIXED], "tests/expected/mixed.txt.w.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_lines() -> TestResult {
+    run(&["-l", MIXED], "tests/expected/mixed.txt.l.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> TestResult {
+    run(&["-L", ATLAMAL], "tests/expected/atlamal.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn widths_stats() -> TestResult {
+    run(&["--stats", WIDTHS], "tests/expected/widths.txt.stats.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn widths_fox_stats() -> TestResult {
+    run(
+        &["-lL", "--stats", WIDTHS, FOX],
+        "tests/expected/widths_fox.stats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn widths_stats_tsv() -> TestResult {
+    run(
+        &["--format=tsv", "-L", "--stats", WIDTHS],
+        "tests/expected/widths.txt.stats.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_stdin_json() -> TestResult {
+    let input = fs::read_to_string(FOX)?;
+    let expected = fs::read_to_string("tests/expected/fox.txt.stdin.json.out")?;
+    Command::cargo_bin(PRG)?
+        .arg("--format=json")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_format() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format=xml", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_file() -> TestResult {
+    run(
+        &["--files0-from", "tests/inputs/files0.txt"],
+        "tests/expected/files0.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin() -> TestResult {
+    let bad = gen_bad_file();
+    let input = format!("{}\0\0{}\0-\0{}", FOX, bad, FOX);
+    let expected = format!(
+        "-:2: invalid zero-length file name\n{}: .* [(]os error 2[)]\n\
+         when reading file names from stdin, no file name of '-' allowed\n",
+        bad
+    );
+    Command::cargo_bin(PRG)?
+        .arg("--files0-from=-")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(expected)?)
+        .stdout(fs::read_to_string("tests/expected/files0.stdin.out")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(unix)]
+#[test]
+fn files0_from_non_utf8_name() -> TestResult {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = std::env::temp_dir().join(gen_bad_file());
+    fs::create_dir(&dir)?;
+    let file = dir.join(OsStr::from_bytes(b"caf\xe9"));
+    fs::write(&file, "one\ntwo\n")?;
+    let mut input = file.as_os_str().as_bytes().to_vec();
+    input.push(0);
+    let result = Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(input)
+        .assert()
+        .try_success();
+    fs::remove_dir_all(&dir)?;
+    result?
+        .stderr("")
+        .stdout(format!("2 {}\n", file.to_string_lossy()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_empty() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("--files0-from=-")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_missing() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("cannot open {} for reading: .* [(]os error 2[)]", bad);
+    Command::cargo_bin(PRG)?
+        .arg(format!("--files0-from={}", bad))
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_with_operands() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from=tests/inputs/files0.txt", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> TestResult {
+    run(
+        &["--total=always", FOX],
+        "tests/expected/fox.txt.total_always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_atlamal_total_only() -> TestResult {
+    run(
+        &["--total=only", FOX, ATLAMAL],
+        "tests/expected/fox_atlamal.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_atlamal_total_only_json() -> TestResult {
+    run(
+        &["--format=json", "--total=only", FOX, ATLAMAL],
+        "tests/expected/fox_atlamal.total_only.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_atlamal_tsv() -> TestResult {
+    run(
+        &["--format=tsv", "-l", FOX, ATLAMAL],
+        "tests/expected/fox_atlamal.l.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_atlamal_total_never() -> TestResult {
+    run(
+        &["--total=never", FOX, ATLAMAL],
+        "tests/expected/fox_atlamal.total_never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn names_stdin_among_files() -> TestResult {
+    let input = fs::read_to_string(ATLAMAL)?;
+    let expected = fs::read_to_string("tests/expected/stdin_fox.l.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-", FOX])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_listed_twice() -> TestResult {
+    let input = fs::read_to_string(ATLAMAL)?;
+    let expected = fs::read_to_string("tests/expected/stdin_fox_stdin.l.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-", FOX, "-"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_read_error() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", FOX])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("tests/inputs: "))
+        .stdout(predicate::str::contains("tests/inputs/fox.txt"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn keeps_argument_order() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/order.out")?;
+    Command::cargo_bin(PRG)?
+        .args([ATLAMAL, FOX, &bad, EMPTY, MIXED, FOX, ATLAMAL])
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(bad))
+        .stdout(expected);
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn test_all() -> TestResult {
@@ -212,3 +488,27 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> TestResult {
+    run(&["-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json() -> TestResult {
+    run(
+        &["--format=json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_lines_chars_csv() -> TestResult {
+    run(
+        &["--format=csv", "-lm", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.lm.csv.out",
+    )
+}